@@ -0,0 +1,86 @@
+use mongodb::bson::Document;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Remembers the `listCollections` entries of collections, by namespace. An entry is fresh for the
+/// TTL, so a collection that was changed outside of the operator is noticed within that time. The
+/// latest entry is what the rest of a reconcile works with, whatever its age.
+pub struct CollectionCache {
+    entries: Mutex<HashMap<String, (Document, Instant)>>,
+    ttl: Duration,
+}
+
+impl CollectionCache {
+    pub fn new(ttl: Duration) -> Self {
+        CollectionCache {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Returns the entry when it was fetched within the TTL.
+    pub fn fresh(&self, namespace: &str) -> Option<Document> {
+        self.entries
+            .lock()
+            .ok()
+            .and_then(|e| e.get(namespace).cloned())
+            .filter(|(_, t)| t.elapsed() < self.ttl)
+            .map(|(d, _)| d)
+    }
+
+    pub fn insert(&self, namespace: &str, entry: Document) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(namespace.to_string(), (entry, Instant::now()));
+        }
+    }
+
+    /// Returns the entry, however old it is.
+    pub fn latest(&self, namespace: &str) -> Option<Document> {
+        self.entries
+            .lock()
+            .ok()
+            .and_then(|e| e.get(namespace).map(|(d, _)| d.clone()))
+    }
+
+    pub fn remove(&self, namespace: &str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(namespace);
+        }
+    }
+
+    /// Forgets all the collections of a database.
+    pub fn remove_database(&self, database: &str) {
+        let prefix = format!("{}.", database);
+
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.retain(|k, _| !k.starts_with(&prefix));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    #[test]
+    fn expired_entry_is_not_fresh_but_still_latest() {
+        let cache = CollectionCache::new(Duration::ZERO);
+
+        cache.insert("db.c", doc! {"name": "c"});
+        assert!(cache.fresh("db.c").is_none());
+        assert_eq!(cache.latest("db.c"), Some(doc! {"name": "c"}));
+    }
+
+    #[test]
+    fn remove_database_keeps_other_databases() {
+        let cache = CollectionCache::new(Duration::from_secs(60));
+
+        cache.insert("db.c", doc! {"name": "c"});
+        cache.insert("db2.c", doc! {"name": "c"});
+        cache.remove_database("db");
+        assert!(cache.latest("db.c").is_none());
+        assert!(cache.fresh("db2.c").is_some());
+    }
+}
@@ -1,11 +1,15 @@
 mod resource;
 
 use kube::CustomResourceExt;
-use resource::MongoCollection;
+use resource::{MongoCollection, MongoCollectionSet, MongoDatabase};
+use std::env;
 
 fn main() {
-    print!(
-        "{}",
-        serde_json::to_string(&MongoCollection::crd()).unwrap()
-    )
+    let crd = match env::args().nth(1).as_deref() {
+        Some("MongoCollectionSet") => MongoCollectionSet::crd(),
+        Some("MongoDatabase") => MongoDatabase::crd(),
+        _ => MongoCollection::crd(),
+    };
+
+    print!("{}", serde_json::to_string(&crd).unwrap())
 }
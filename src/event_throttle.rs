@@ -0,0 +1,77 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Suppresses an event that was already published for the same object, with the same reason and
+/// note, within the interval.
+pub struct EventThrottle {
+    interval: Duration,
+    published: Mutex<HashMap<(String, String, String), Instant>>,
+}
+
+impl EventThrottle {
+    /// A zero interval lets all events through.
+    pub fn new(interval: Duration) -> Self {
+        EventThrottle {
+            interval,
+            published: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns whether the event should be published now, in which case it is remembered.
+    pub fn allow(&self, object: &str, reason: &str, note: &str) -> bool {
+        if self.interval.is_zero() {
+            return true;
+        }
+
+        let Ok(mut published) = self.published.lock() else {
+            return true;
+        };
+        let now = Instant::now();
+
+        published.retain(|_, t| now.duration_since(*t) < self.interval);
+
+        match published.entry((object.to_string(), reason.to_string(), note.to_string())) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(e) => {
+                e.insert(now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+
+    #[test]
+    fn event_is_published_again_after_the_interval() {
+        let throttle = EventThrottle::new(Duration::from_millis(50));
+
+        assert!(throttle.allow("ns/orders", "Failed", "boom"));
+        assert!(!throttle.allow("ns/orders", "Failed", "boom"));
+        sleep(Duration::from_millis(60));
+        assert!(throttle.allow("ns/orders", "Failed", "boom"));
+    }
+
+    #[test]
+    fn other_events_are_not_throttled() {
+        let throttle = EventThrottle::new(Duration::from_secs(60));
+
+        assert!(throttle.allow("ns/orders", "Failed", "boom"));
+        assert!(throttle.allow("ns/orders", "Failed", "bang"));
+        assert!(throttle.allow("ns/orders", "Created", "boom"));
+        assert!(throttle.allow("ns/invoices", "Failed", "boom"));
+    }
+
+    #[test]
+    fn zero_interval_lets_everything_through() {
+        let throttle = EventThrottle::new(Duration::ZERO);
+
+        assert!(throttle.allow("ns/orders", "Failed", "boom"));
+        assert!(throttle.allow("ns/orders", "Failed", "boom"));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::resource::{Collation, MongoCollectionSpec};
+use anyhow::Result;
+use k8s_openapi::serde::{Deserialize, Serialize};
+use mongodb::bson::{from_bson, Bson};
+use mongodb::IndexModel;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+/// The indexes a MongoDB server listed for a collection, together with the spec they were created
+/// from. Reconciling the spec against them should change nothing.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fixture {
+    /// The collation listCollections reported for the collection. Without it the collection has
+    /// the one of the spec.
+    pub collation: Option<Collation>,
+    /// The output of listIndexes in relaxed extended JSON.
+    pub indexes: Vec<Value>,
+    pub server_version: String,
+    pub spec: MongoCollectionSpec,
+}
+
+impl Fixture {
+    pub fn index_models(&self) -> Result<Vec<IndexModel>> {
+        self.indexes
+            .iter()
+            .map(|v| Ok(from_bson(Bson::try_from(v.clone())?)?))
+            .collect()
+    }
+}
+
+/// Loads the JSON files in the directory in the order of their names.
+pub fn load(directory: &str) -> Result<Vec<(String, Fixture)>> {
+    let mut paths = fs::read_dir(directory)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+
+    paths.retain(|p| p.extension().is_some_and(|e| e == "json"));
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|p| {
+            Ok((
+                p.display().to_string(),
+                serde_json::from_str(&fs::read_to_string(p)?)?,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,61 @@
+use crate::plan::Target;
+use k8s_openapi::serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Keeps the most recent reconcile outcomes per object in memory.
+pub struct History {
+    capacity: usize,
+    records: Mutex<HashMap<(String, String), VecDeque<Record>>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    pub actions: Vec<String>,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+    /// Where the reconcile went, when it could be resolved.
+    pub target: Option<Target>,
+    pub timestamp: String,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            capacity,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn add(&self, namespace: &str, name: &str, record: Record) {
+        if let Ok(mut records) = self.records.lock() {
+            let entries = records
+                .entry((namespace.to_string(), name.to_string()))
+                .or_default();
+
+            if entries.len() >= self.capacity {
+                entries.pop_front();
+            }
+
+            entries.push_back(record);
+        }
+    }
+
+    pub fn get(&self, namespace: &str, name: &str) -> Vec<Record> {
+        self.records
+            .lock()
+            .ok()
+            .and_then(|r| {
+                r.get(&(namespace.to_string(), name.to_string()))
+                    .map(|e| e.iter().cloned().collect())
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn remove(&self, namespace: &str, name: &str) {
+        if let Ok(mut records) = self.records.lock() {
+            records.remove(&(namespace.to_string(), name.to_string()));
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// The upper bounds of the buckets of the command duration histogram, in seconds.
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The per-collection gauges, the number of unwatchable namespaces, the server status, the
+/// topology, the expiry of the client certificate and the durations of MongoDB commands, rendered
+/// in the OpenMetrics text format.
+pub struct Metrics {
+    /// In seconds since the epoch.
+    client_cert_expiry: Mutex<Option<i64>>,
+    /// By command name and outcome.
+    command_durations: Mutex<BTreeMap<(String, String), Histogram>>,
+    collections: Mutex<BTreeMap<(String, String), CollectionState>>,
+    server_status: Mutex<Option<(String, ServerStatus)>>,
+    /// The topology type and the address of the primary, if there is one.
+    topology: Mutex<Option<(String, Option<String>)>>,
+    unwatchable_namespaces: AtomicUsize,
+}
+
+/// The counters of the serverStatus command that was polled last.
+pub struct ServerStatus {
+    pub connections_available: i64,
+    pub connections_current: i64,
+    pub connections_total_created: i64,
+    pub inserts: i64,
+    /// In seconds since the epoch.
+    pub polled_at: i64,
+    pub queries: i64,
+    pub updates: i64,
+}
+
+/// The bucket counts aren't cumulative.
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+struct CollectionState {
+    collection: String,
+    in_sync: bool,
+    pending_changes: usize,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            client_cert_expiry: Mutex::new(None),
+            command_durations: Mutex::new(BTreeMap::new()),
+            collections: Mutex::new(BTreeMap::new()),
+            server_status: Mutex::new(None),
+            topology: Mutex::new(None),
+            unwatchable_namespaces: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn client_cert_expiry(&self) -> Option<i64> {
+        self.client_cert_expiry.lock().ok().and_then(|e| *e)
+    }
+
+    /// The number of unused connections of the server status that was polled last.
+    pub fn connections_available(&self) -> Option<i64> {
+        self.server_status
+            .lock()
+            .ok()?
+            .as_ref()
+            .map(|(_, s)| s.connections_available)
+    }
+
+    /// The outcome is either "success" or "failure".
+    pub fn observe_command(&self, command: &str, outcome: &str, seconds: f64) {
+        if let Ok(mut durations) = self.command_durations.lock() {
+            let histogram = durations
+                .entry((command.to_string(), outcome.to_string()))
+                .or_default();
+
+            if let Some(i) = DURATION_BUCKETS.iter().position(|b| seconds <= *b) {
+                histogram.buckets[i] += 1;
+            }
+
+            histogram.count += 1;
+            histogram.sum += seconds;
+        }
+    }
+
+    pub fn remove(&self, namespace: &str, name: &str) {
+        if let Ok(mut collections) = self.collections.lock() {
+            collections.remove(&(namespace.to_string(), name.to_string()));
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut result = String::new();
+
+        if let Some(expiry) = self.client_cert_expiry() {
+            let _ = writeln!(
+                result,
+                "# TYPE mongo_collections_client_cert_expiry_timestamp gauge"
+            );
+            let _ = writeln!(
+                result,
+                "# HELP mongo_collections_client_cert_expiry_timestamp When the client certificate \
+                for MongoDB expires, in seconds since the epoch."
+            );
+            let _ = writeln!(
+                result,
+                "mongo_collections_client_cert_expiry_timestamp {expiry}"
+            );
+        }
+
+        if let Ok(durations) = self.command_durations.lock() {
+            command_durations(&mut result, &durations);
+        }
+
+        if let Ok(collections) = self.collections.lock() {
+            gauge(
+                &mut result,
+                "mongo_collections_in_sync",
+                "Whether the last reconcile converged without pending changes.",
+                &collections,
+                |s| usize::from(s.in_sync),
+            );
+            gauge(
+                &mut result,
+                "mongo_collections_pending_changes",
+                "The number of creates, drops and collMods that are still outstanding.",
+                &collections,
+                |s| s.pending_changes,
+            );
+        }
+
+        let server_status = self.server_status.lock().ok();
+
+        if let Some((database, status)) = server_status.as_ref().and_then(|s| s.as_ref()) {
+            for (name, help, value) in [
+                (
+                    "mongo_server_connections_available",
+                    "The number of unused connections.",
+                    status.connections_available,
+                ),
+                (
+                    "mongo_server_connections_current",
+                    "The number of open connections.",
+                    status.connections_current,
+                ),
+                (
+                    "mongo_server_connections_total_created",
+                    "The number of connections created since the server started.",
+                    status.connections_total_created,
+                ),
+                (
+                    "mongo_server_opcounters_insert",
+                    "The number of inserts since the server started.",
+                    status.inserts,
+                ),
+                (
+                    "mongo_server_opcounters_query",
+                    "The number of queries since the server started.",
+                    status.queries,
+                ),
+                (
+                    "mongo_server_opcounters_update",
+                    "The number of updates since the server started.",
+                    status.updates,
+                ),
+                (
+                    "mongo_server_status_polled_at_seconds",
+                    "When the server status was polled last.",
+                    status.polled_at,
+                ),
+            ] {
+                let _ = writeln!(result, "# TYPE {name} gauge");
+                let _ = writeln!(result, "# HELP {name} {help}");
+                let _ = writeln!(result, "{name}{{db=\"{}\"}} {value}", escape(database));
+            }
+        }
+
+        let topology = self.topology.lock().ok();
+
+        if let Some((topology_type, primary)) = topology.as_ref().and_then(|t| t.as_ref()) {
+            let _ = writeln!(result, "# TYPE mongo_collections_topology_type gauge");
+            let _ = writeln!(
+                result,
+                "# HELP mongo_collections_topology_type The type of the MongoDB topology and its \
+                primary."
+            );
+            let _ = writeln!(
+                result,
+                "mongo_collections_topology_type{{type=\"{}\",primary=\"{}\"}} 1",
+                escape(topology_type),
+                escape(primary.as_deref().unwrap_or(""))
+            );
+        }
+
+        let _ = writeln!(
+            result,
+            "# TYPE mongo_collections_unwatchable_namespaces gauge"
+        );
+        let _ = writeln!(
+            result,
+            "# HELP mongo_collections_unwatchable_namespaces The number of watched namespaces with \
+            missing permissions."
+        );
+        let _ = writeln!(
+            result,
+            "mongo_collections_unwatchable_namespaces {}",
+            self.unwatchable_namespaces.load(Ordering::Relaxed)
+        );
+        result.push_str("# EOF\n");
+        result
+    }
+
+    pub fn set(
+        &self,
+        namespace: &str,
+        name: &str,
+        collection: &str,
+        in_sync: bool,
+        pending_changes: usize,
+    ) {
+        if let Ok(mut collections) = self.collections.lock() {
+            collections.insert(
+                (namespace.to_string(), name.to_string()),
+                CollectionState {
+                    collection: collection.to_string(),
+                    in_sync,
+                    pending_changes,
+                },
+            );
+        }
+    }
+
+    pub fn set_client_cert_expiry(&self, expiry: i64) {
+        if let Ok(mut client_cert_expiry) = self.client_cert_expiry.lock() {
+            *client_cert_expiry = Some(expiry);
+        }
+    }
+
+    pub fn set_server_status(&self, database: &str, status: ServerStatus) {
+        if let Ok(mut server_status) = self.server_status.lock() {
+            *server_status = Some((database.to_string(), status));
+        }
+    }
+
+    pub fn set_topology(&self, topology_type: &str, primary: Option<&str>) {
+        if let Ok(mut topology) = self.topology.lock() {
+            *topology = Some((topology_type.to_string(), primary.map(str::to_string)));
+        }
+    }
+
+    pub fn set_unwatchable_namespaces(&self, count: usize) {
+        self.unwatchable_namespaces.store(count, Ordering::Relaxed);
+    }
+}
+
+fn command_durations(result: &mut String, durations: &BTreeMap<(String, String), Histogram>) {
+    let name = "mongo_collections_command_duration_seconds";
+
+    let _ = writeln!(result, "# TYPE {name} histogram");
+    let _ = writeln!(
+        result,
+        "# HELP {name} How long the MongoDB commands of the operator took."
+    );
+
+    for ((command, outcome), histogram) in durations {
+        let labels = format!(
+            "command=\"{}\",outcome=\"{}\"",
+            escape(command),
+            escape(outcome)
+        );
+        let mut cumulative = 0;
+
+        for (bound, count) in DURATION_BUCKETS.iter().zip(histogram.buckets) {
+            cumulative += count;
+            let _ = writeln!(
+                result,
+                "{name}_bucket{{{labels},le=\"{bound:?}\"}} {cumulative}"
+            );
+        }
+
+        let _ = writeln!(
+            result,
+            "{name}_bucket{{{labels},le=\"+Inf\"}} {}",
+            histogram.count
+        );
+        let _ = writeln!(result, "{name}_sum{{{labels}}} {}", histogram.sum);
+        let _ = writeln!(result, "{name}_count{{{labels}}} {}", histogram.count);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn gauge<F>(
+    result: &mut String,
+    name: &str,
+    help: &str,
+    collections: &BTreeMap<(String, String), CollectionState>,
+    value: F,
+) where
+    F: Fn(&CollectionState) -> usize,
+{
+    let _ = writeln!(result, "# TYPE {name} gauge");
+    let _ = writeln!(result, "# HELP {name} {help}");
+
+    for ((namespace, n), state) in collections {
+        let _ = writeln!(
+            result,
+            "{name}{{namespace=\"{}\",name=\"{}\",collection=\"{}\"}} {}",
+            escape(namespace),
+            escape(n),
+            escape(&state.collection),
+            value(state)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_durations_are_cumulative() {
+        let metrics = Metrics::new();
+
+        metrics.observe_command("createIndexes", "success", 0.02);
+        metrics.observe_command("createIndexes", "success", 20.0);
+
+        let rendered = metrics.render();
+        let name = "mongo_collections_command_duration_seconds";
+        let labels = "command=\"createIndexes\",outcome=\"success\"";
+
+        assert!(rendered.contains(&format!("{name}_bucket{{{labels},le=\"0.01\"}} 0\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{{labels},le=\"0.025\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{{labels},le=\"10.0\"}} 1\n")));
+        assert!(rendered.contains(&format!("{name}_bucket{{{labels},le=\"+Inf\"}} 2\n")));
+        assert!(rendered.contains(&format!("{name}_count{{{labels}}} 2\n")));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let metrics = Metrics::new();
+
+        metrics.set("ns", "a\"b", "c\\d", true, 0);
+        assert!(metrics.render().contains(
+            "mongo_collections_in_sync{namespace=\"ns\",name=\"a\\\"b\",collection=\"c\\\\d\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn pending_changes_are_reported_until_removed() {
+        let metrics = Metrics::new();
+        let labels = "{namespace=\"ns\",name=\"orders\",collection=\"orders\"}";
+
+        metrics.set("ns", "orders", "orders", false, 3);
+
+        let rendered = metrics.render();
+
+        assert!(rendered.contains(&format!("mongo_collections_in_sync{labels} 0\n")));
+        assert!(rendered.contains(&format!("mongo_collections_pending_changes{labels} 3\n")));
+
+        metrics.remove("ns", "orders");
+        assert!(!metrics.render().contains(labels));
+    }
+
+    #[test]
+    fn render_ends_with_eof() {
+        assert!(Metrics::new().render().ends_with("# EOF\n"));
+    }
+}
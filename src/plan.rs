@@ -0,0 +1,509 @@
+use crate::resource::{
+    Collation, Direction, Index, Key, MongoCollectionSpec, Options, SearchIndex,
+};
+use k8s_openapi::serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// What a reconcile would do to a collection.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Plan {
+    pub collection: String,
+    pub create_collection: bool,
+    pub indexes: IndexPlan,
+    pub search_indexes: SearchIndexPlan,
+    pub target: Target,
+}
+
+/// Where in MongoDB a resource is reconciled and how that was resolved.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    pub collection: String,
+    /// Either "spec.name" or "metadata.name".
+    pub collection_source: String,
+    pub database: String,
+    /// Either "connectionRef" followed by the name of the MongoDatabase, or "configuration".
+    pub database_source: String,
+    /// The database and collection names joined with a dot.
+    pub namespace: String,
+}
+
+/// The index changes that bring a collection in line with its spec.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexPlan {
+    pub create: Vec<Index>,
+    pub drop: Vec<String>,
+    pub hide: Vec<String>,
+    /// Existing indexes that only have to become unique, which can be done without a rebuild.
+    pub make_unique: Vec<String>,
+    /// Existing 2dsphere indexes that are rebuilt because another version is specified, as
+    /// "name: live -> desired". They are also in create and drop.
+    pub sphere_index_versions: Vec<String>,
+    pub unhide: Vec<String>,
+}
+
+impl IndexPlan {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.create.len()
+            + self.drop.len()
+            + self.hide.len()
+            + self.make_unique.len()
+            + self.unhide.len()
+    }
+
+    /// Without prepareUnique an index that has to become unique is dropped and created again. The
+    /// index MongoDB creates on the metaField and timeField of a time series collection is left
+    /// alone. A specified index with the same keys is considered to be that index. The fields of
+    /// the found indexes are first renamed with the field aliases of the spec. A collation that
+    /// is the same as the one the collection actually has is ignored on both sides. Without
+    /// preserveUnmanagedIndexes, unmanaged indexes that are hidden are unhidden instead of dropped.
+    /// They and the released ones, which were unhidden before, are only dropped when
+    /// dropHiddenUnmanagedIndexes is set.
+    pub fn new(
+        spec: &MongoCollectionSpec,
+        found: &[Index],
+        collation: Option<&Collation>,
+        prepare_unique: bool,
+        released: &[String],
+    ) -> Self {
+        let found = &without_inherited_collation(collation, &aliased(spec, found));
+        let specified = spec
+            .indexes
+            .as_deref()
+            .map(|i| without_inherited_collation(collation, i));
+
+        specified
+            .as_deref()
+            .map_or_else(Self::default, |specified| {
+                let automatic =
+                    time_series_keys(spec).filter(|keys| found.iter().any(|f| f.keys == *keys));
+                let is_automatic = |i: &Index| automatic.as_ref().is_some_and(|k| i.keys == *k);
+                let preserve = spec.preserve_unmanaged_indexes.unwrap_or(false);
+                let drop_hidden = spec.drop_hidden_unmanaged_indexes.unwrap_or(false);
+                let kept = |o: &Options| {
+                    !drop_hidden
+                        && (is_hidden(o) || o.name.as_ref().is_some_and(|n| released.contains(n)))
+                };
+                let converted: Vec<&Index> = found
+                    .iter()
+                    .filter(|f| {
+                        prepare_unique && specified.iter().any(|i| only_unique_differs(i, f))
+                    })
+                    .collect();
+                let remaining: Vec<Index> = found
+                    .iter()
+                    .filter(|f| !converted.contains(f) && !is_automatic(f))
+                    .cloned()
+                    .collect();
+                let create: Vec<Index> = specified
+                    .iter()
+                    .filter(|i| {
+                        !found.contains(i)
+                            && !converted.iter().any(|f| only_unique_differs(i, f))
+                            && !is_automatic(i)
+                    })
+                    .cloned()
+                    .collect();
+                let sphere_index_versions = sphere_index_versions(&create, found);
+
+                Self {
+                    create,
+                    drop: if preserve {
+                        Vec::new()
+                    } else {
+                        unmanaged(specified, &remaining, |o| !kept(o))
+                    },
+                    hide: if preserve && spec.hide_all_unmanaged == Some(true) {
+                        unmanaged(specified, &remaining, |o| !is_hidden(o))
+                    } else {
+                        Vec::new()
+                    },
+                    make_unique: converted
+                        .iter()
+                        .flat_map(|f| f.options.as_ref().and_then(|o| o.name.clone()))
+                        .collect(),
+                    sphere_index_versions,
+                    unhide: if (preserve && spec.hide_all_unmanaged == Some(false))
+                        || (!preserve && !drop_hidden)
+                    {
+                        unmanaged(specified, &remaining, is_hidden)
+                    } else {
+                        Vec::new()
+                    },
+                }
+            })
+            .sorted()
+    }
+
+    // The indexes are processed in a stable order, so the logs of reconciles can be compared.
+    fn sorted(mut self) -> Self {
+        self.create.sort_by_cached_key(sort_key);
+        self.drop.sort();
+        self.hide.sort();
+        self.make_unique.sort();
+        self.sphere_index_versions.sort();
+        self.unhide.sort();
+        self
+    }
+}
+
+/// The Atlas Search index changes that bring a collection in line with its spec. Search indexes are
+/// matched by name.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndexPlan {
+    pub create: Vec<SearchIndex>,
+    pub drop: Vec<String>,
+    /// Existing search indexes with another definition, which are updated in place.
+    pub update: Vec<SearchIndex>,
+}
+
+impl SearchIndexPlan {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.create.len() + self.drop.len() + self.update.len()
+    }
+
+    pub fn new(spec: &MongoCollectionSpec, found: &[SearchIndex]) -> Self {
+        spec.search_indexes
+            .as_deref()
+            .map_or_else(Self::default, |specified| Self {
+                create: specified
+                    .iter()
+                    .filter(|s| !found.iter().any(|f| f.name == s.name))
+                    .cloned()
+                    .collect(),
+                drop: found
+                    .iter()
+                    .filter(|f| !specified.iter().any(|s| s.name == f.name))
+                    .map(|f| f.name.clone())
+                    .collect(),
+                update: specified
+                    .iter()
+                    .filter(|s| {
+                        found
+                            .iter()
+                            .any(|f| f.name == s.name && f.definition != effective_definition(s))
+                    })
+                    .cloned()
+                    .collect(),
+            })
+            .sorted()
+    }
+
+    fn sorted(mut self) -> Self {
+        self.create.sort_by(|a, b| a.name.cmp(&b.name));
+        self.drop.sort();
+        self.update.sort_by(|a, b| a.name.cmp(&b.name));
+        self
+    }
+}
+
+// The found indexes as if their fields had the canonical names.
+fn aliased(spec: &MongoCollectionSpec, found: &[Index]) -> Vec<Index> {
+    let Some(aliases) = spec.field_aliases.as_ref().filter(|a| !a.is_empty()) else {
+        return found.to_vec();
+    };
+    let canonical = |field: &String| aliases.get(field).unwrap_or(field).clone();
+
+    found
+        .iter()
+        .map(|f| {
+            let mut index = f.clone();
+
+            for k in index.keys.iter_mut() {
+                k.field = canonical(&k.field);
+            }
+
+            if let Some(w) = index.options.as_mut().and_then(|o| o.weights.as_mut()) {
+                *w = w.iter().map(|(k, v)| (canonical(k), *v)).collect();
+            }
+
+            index
+        })
+        .collect()
+}
+
+/// The definition of a search index with its stored source, as MongoDB lists it.
+pub fn effective_definition(index: &SearchIndex) -> Map<String, Value> {
+    let mut definition = index.definition.clone();
+
+    if let Some(s) = index.stored_source.as_ref() {
+        definition.insert("storedSource".to_string(), json!(s));
+    }
+
+    definition
+}
+
+fn is_hidden(options: &Options) -> bool {
+    options.hidden.unwrap_or(false)
+}
+
+fn is_unique(index: &Index) -> bool {
+    index
+        .options
+        .as_ref()
+        .and_then(|o| o.unique)
+        .unwrap_or(false)
+}
+
+// Whether the specified index is the found one, but unique.
+fn only_unique_differs(specified: &Index, found: &Index) -> bool {
+    let mut relaxed = specified.clone();
+
+    if let Some(o) = relaxed.options.as_mut() {
+        o.unique = found.options.as_ref().and_then(|f| f.unique);
+    }
+
+    is_unique(specified) && !is_unique(found) && relaxed == *found
+}
+
+// The name when there is one, otherwise the fields of the keys.
+fn sort_key(index: &Index) -> String {
+    index
+        .options
+        .as_ref()
+        .and_then(|o| o.name.clone())
+        .unwrap_or_else(|| {
+            index
+                .keys
+                .iter()
+                .map(|k| k.field.as_str())
+                .collect::<Vec<_>>()
+                .join("_")
+        })
+}
+
+fn sphere_index_version(index: &Index) -> Option<u32> {
+    index.options.as_ref()?.sphere_index_version
+}
+
+fn sphere_index_versions(create: &[Index], found: &[Index]) -> Vec<String> {
+    create
+        .iter()
+        .filter_map(|i| {
+            let desired = sphere_index_version(i)?;
+            let f = found.iter().find(|f| f.keys == i.keys)?;
+            let live = sphere_index_version(f)?;
+
+            (live != desired).then(|| {
+                format!(
+                    "{}: {live} -> {desired}",
+                    f.options
+                        .as_ref()
+                        .and_then(|o| o.name.as_deref())
+                        .unwrap_or_default()
+                )
+            })
+        })
+        .collect()
+}
+
+fn time_series_keys(spec: &MongoCollectionSpec) -> Option<Vec<Key>> {
+    let time_series = spec.time_series.as_ref()?;
+
+    Some(
+        [time_series.meta_field.as_ref()?, &time_series.time_field]
+            .into_iter()
+            .map(|field| Key {
+                direction: Some(Direction::Ascending),
+                field: field.clone(),
+                index_type: None,
+            })
+            .collect(),
+    )
+}
+
+fn unmanaged<P>(specified: &[Index], found: &[Index], predicate: P) -> Vec<String>
+where
+    P: Fn(&Options) -> bool,
+{
+    found
+        .iter()
+        .filter(|i| !specified.contains(i))
+        .flat_map(|i| i.options.as_ref())
+        .filter(|o| predicate(o))
+        .flat_map(|o| o.name.clone())
+        .collect()
+}
+
+// An index without a collation inherits the one of the collection, which MongoDB then lists with
+// the index.
+fn without_inherited_collation(collation: Option<&Collation>, indexes: &[Index]) -> Vec<Index> {
+    indexes
+        .iter()
+        .map(|i| {
+            let mut index = i.clone();
+
+            if let Some(o) = index
+                .options
+                .as_mut()
+                .filter(|o| o.collation.is_some() && o.collation.as_ref() == collation)
+            {
+                o.collation = None;
+            }
+
+            index
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{from_value, json, Value};
+
+    fn collation(locale: &str) -> Collation {
+        from_value(json!({"locale": locale})).unwrap()
+    }
+
+    fn indexes(value: Value) -> Vec<Index> {
+        from_value(value).unwrap()
+    }
+
+    fn spec(value: Value) -> MongoCollectionSpec {
+        from_value(value).unwrap()
+    }
+
+    #[test]
+    fn collation_of_collection_is_inherited() {
+        let spec = spec(json!({"indexes": [{"keys": [{"a": 1}]}]}));
+        let found = indexes(json!([
+            {"keys": [{"a": 1}], "options": {"name": "a_1", "collation": {"locale": "de"}}}
+        ]));
+        let plan = IndexPlan::new(&spec, &found, Some(&collation("de")), true, &[]);
+
+        assert_eq!(plan.len(), 0, "{plan:?}");
+    }
+
+    #[test]
+    fn hidden_unmanaged_index_is_unhidden_instead_of_dropped() {
+        let spec = spec(json!({"indexes": [{"keys": [{"a": 1}]}]}));
+        let found = indexes(json!([
+            {"keys": [{"a": 1}], "options": {"name": "a_1"}},
+            {"keys": [{"b": 1}], "options": {"name": "b_1", "hidden": true}}
+        ]));
+        let plan = IndexPlan::new(&spec, &found, None, true, &[]);
+
+        assert!(plan.drop.is_empty());
+        assert_eq!(plan.unhide, vec!["b_1".to_string()]);
+    }
+
+    #[test]
+    fn index_on_an_aliased_field_is_kept() {
+        let found = indexes(json!([{"keys": [{"ts": 1}], "options": {"name": "ts_1"}}]));
+        let aliased = spec(json!({
+            "fieldAliases": {"ts": "timestamp"},
+            "indexes": [{"keys": [{"timestamp": 1}]}]
+        }));
+        let plan = IndexPlan::new(&aliased, &found, None, true, &[]);
+
+        assert_eq!(plan.len(), 0, "{plan:?}");
+
+        let plan = IndexPlan::new(
+            &spec(json!({"indexes": [{"keys": [{"timestamp": 1}]}]})),
+            &found,
+            None,
+            true,
+            &[],
+        );
+
+        assert_eq!(plan.create.len(), 1);
+        assert_eq!(plan.drop, vec!["ts_1".to_string()]);
+    }
+
+    #[test]
+    fn index_that_only_lacks_unique_is_made_unique() {
+        let spec = spec(json!({"indexes": [{"keys": [{"a": 1}], "options": {"unique": true}}]}));
+        let found = indexes(json!([{"keys": [{"a": 1}], "options": {"name": "a_1"}}]));
+        let plan = IndexPlan::new(&spec, &found, None, true, &[]);
+
+        assert_eq!(plan.make_unique, vec!["a_1".to_string()]);
+        assert!(plan.create.is_empty());
+        assert!(plan.drop.is_empty());
+
+        let plan = IndexPlan::new(&spec, &found, None, false, &[]);
+
+        assert!(plan.make_unique.is_empty());
+        assert_eq!(plan.create.len(), 1);
+        assert_eq!(plan.drop, vec!["a_1".to_string()]);
+    }
+
+    #[test]
+    fn indexes_are_created_again_after_capped_migration() {
+        let spec = spec(json!({
+            "capped": true,
+            "size": 4096,
+            "indexes": [{"keys": [{"a": 1}]}, {"keys": [{"b": -1}], "options": {"unique": true}}]
+        }));
+        // The clone only has the _id index, which isn't listed.
+        let plan = IndexPlan::new(&spec, &[], None, true, &[]);
+
+        assert_eq!(plan.create.len(), 2);
+        assert_eq!(plan.len(), 2);
+    }
+
+    #[test]
+    fn other_collation_is_not_inherited() {
+        let spec = spec(json!({"indexes": [{"keys": [{"a": 1}]}]}));
+        let found = indexes(json!([
+            {"keys": [{"a": 1}], "options": {"name": "a_1", "collation": {"locale": "fr"}}}
+        ]));
+        let plan = IndexPlan::new(&spec, &found, Some(&collation("de")), true, &[]);
+
+        assert_eq!(plan.create.len(), 1);
+        assert_eq!(plan.drop, vec!["a_1".to_string()]);
+    }
+
+    #[test]
+    fn preserved_unmanaged_index_is_hidden() {
+        let spec = spec(json!({
+            "hideAllUnmanaged": true,
+            "indexes": [],
+            "preserveUnmanagedIndexes": true
+        }));
+        let found = indexes(json!([
+            {"keys": [{"b": 1}], "options": {"name": "b_1"}},
+            {"keys": [{"c": 1}], "options": {"name": "c_1", "hidden": true}}
+        ]));
+        let plan = IndexPlan::new(&spec, &found, None, true, &[]);
+
+        assert!(plan.drop.is_empty());
+        assert_eq!(plan.hide, vec!["b_1".to_string()]);
+    }
+
+    #[test]
+    fn preserved_unmanaged_index_is_kept() {
+        let spec = spec(json!({
+            "indexes": [{"keys": [{"a": 1}]}],
+            "preserveUnmanagedIndexes": true
+        }));
+        let found = indexes(json!([{"keys": [{"b": 1}], "options": {"name": "b_1"}}]));
+        let plan = IndexPlan::new(&spec, &found, None, true, &[]);
+
+        assert_eq!(plan.create.len(), 1);
+        assert!(plan.drop.is_empty());
+    }
+
+    #[test]
+    fn unmanaged_index_is_dropped() {
+        let spec = spec(json!({"indexes": [{"keys": [{"a": 1}]}]}));
+        let found = indexes(json!([
+            {"keys": [{"a": 1}], "options": {"name": "a_1"}},
+            {"keys": [{"b": 1}], "options": {"name": "b_1"}}
+        ]));
+        let plan = IndexPlan::new(&spec, &found, None, true, &[]);
+
+        assert!(plan.create.is_empty());
+        assert_eq!(plan.drop, vec!["b_1".to_string()]);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::plan::IndexPlan;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Remembers the index plans for combinations of a spec and the live indexes. The key is their
+/// serialized form, so a hit always has the same input. The least recently used plan is evicted
+/// when the cache is full.
+pub struct PlanCache {
+    capacity: usize,
+    plans: Mutex<Plans>,
+}
+
+#[derive(Default)]
+struct Plans {
+    entries: HashMap<String, (IndexPlan, u64)>,
+    tick: u64,
+}
+
+impl PlanCache {
+    pub fn new(capacity: usize) -> Self {
+        PlanCache {
+            capacity,
+            plans: Mutex::new(Plans::default()),
+        }
+    }
+
+    /// Returns the cached plan or computes and caches it.
+    pub fn get_or_insert<F>(&self, key: String, compute: F) -> IndexPlan
+    where
+        F: FnOnce() -> IndexPlan,
+    {
+        let Ok(mut plans) = self.plans.lock() else {
+            return compute();
+        };
+
+        plans.tick += 1;
+
+        let tick = plans.tick;
+
+        if let Some((plan, used)) = plans.entries.get_mut(&key) {
+            *used = tick;
+            return plan.clone();
+        }
+
+        if plans.entries.len() >= self.capacity {
+            let oldest = plans
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+
+            if let Some(k) = oldest {
+                plans.entries.remove(&k);
+            }
+        }
+
+        let plan = compute();
+
+        plans.entries.insert(key, (plan.clone(), tick));
+        plan
+    }
+}
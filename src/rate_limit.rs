@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+
+/// A token bucket that spreads the MongoDB writes of all reconciles over time.
+pub struct RateLimiter {
+    burst: f64,
+    rate: Option<f64>,
+    state: Mutex<State>,
+}
+
+struct State {
+    last: Instant,
+    tokens: f64,
+}
+
+impl RateLimiter {
+    /// Without a rate, in operations per second, there is no limit.
+    pub fn new(rate: Option<f64>, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+
+        Self {
+            burst,
+            rate: rate.filter(|r| *r > 0.0),
+            state: Mutex::new(State {
+                last: Instant::now(),
+                tokens: burst,
+            }),
+        }
+    }
+
+    /// Waits until a token is available. The lock is held while waiting, so waiters are served
+    /// in order.
+    pub async fn acquire(&self) {
+        if let Some(rate) = self.rate {
+            let mut state = self.state.lock().await;
+            let now = Instant::now();
+
+            state.tokens = (state.tokens + now.duration_since(state.last).as_secs_f64() * rate)
+                .min(self.burst);
+            state.last = now;
+
+            if state.tokens < 1.0 {
+                sleep(Duration::from_secs_f64((1.0 - state.tokens) / rate)).await;
+                state.tokens = 1.0;
+                state.last = Instant::now();
+            }
+
+            state.tokens -= 1.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn burst_is_served_right_away() {
+        let limiter = RateLimiter::new(Some(1.0), 3);
+        let started = Instant::now();
+
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn operations_after_the_burst_wait_for_a_token() {
+        let limiter = RateLimiter::new(Some(20.0), 2);
+        let started = Instant::now();
+
+        for _ in 0..4 {
+            limiter.acquire().await;
+        }
+
+        // Two tokens come from the burst and the other two take 50ms each.
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn without_a_rate_there_is_no_limit() {
+        let limiter = RateLimiter::new(None, 1);
+        let started = Instant::now();
+
+        for _ in 0..1000 {
+            limiter.acquire().await;
+        }
+
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -1,9 +1,11 @@
-use k8s_openapi::serde::{Deserialize, Serialize};
+use k8s_openapi::serde::de::Error;
+use k8s_openapi::serde::{Deserialize, Deserializer, Serialize, Serializer};
 use kube::CustomResource;
 use kube_operator_util::status::Status;
-use schemars::{JsonSchema, JsonSchema_repr};
-use serde_json::{Map, Value};
+use schemars::{json_schema, JsonSchema, JsonSchema_repr, Schema, SchemaGenerator};
+use serde_json::{json, Map, Value};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::borrow::Cow;
 use std::cmp::PartialEq;
 use std::collections::BTreeMap;
 use CollationAlternate::NonIgnorable;
@@ -11,6 +13,8 @@ use CollationCaseFirst::Off;
 use CollationMaxVariable::Punct;
 use CollationStrength::Tertiary;
 
+/// Changing the annotation reconcile.pincette.net/requestedAt, e.g. to the current time, triggers
+/// an immediate reconcile, also when the resource is in an error state.
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[kube(
     kind = "MongoCollection",
@@ -23,22 +27,162 @@ use CollationStrength::Tertiary;
     printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.phase"}"#,
     printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
 )]
-#[kube(status = "Status")]
+#[kube(status = "MongoCollectionStatus")]
 #[serde(rename_all = "camelCase")]
 pub struct MongoCollectionSpec {
+    /// Lets the operator convert an existing collection that isn't capped into a capped one with
+    /// cloneCollectionAsCapped. The collection is replaced by the clone.
+    pub allow_capped_migration: Option<bool>,
     pub capped: Option<bool>,
     pub change_stream_pre_and_post_images: Option<bool>,
     pub clustered: Option<bool>,
     pub collation: Option<Collation>,
+    /// The commit quorum for index builds, which defaults to votingMembers. It is ignored on a
+    /// standalone server.
+    pub commit_quorum: Option<String>,
+    /// The name of a MongoDatabase resource. The collection is then created in that database
+    /// instead of the configured one.
+    pub connection_ref: Option<String>,
+    /// Confirms that an empty list of indexes should drop all indexes of the collection.
+    pub confirm_empty_indexes: Option<bool>,
+    /// Lets the operator drop the unmanaged indexes that are hidden, or that it unhid, when
+    /// preserveUnmanagedIndexes is off. Without it they are unhidden and kept.
+    pub drop_hidden_unmanaged_indexes: Option<bool>,
     pub expire_after_seconds: Option<u64>,
+    /// Collection options the operator doesn't know yet. They are added to the other options when
+    /// the collection is created. Changing them afterwards has no effect.
+    pub extra_options: Option<Map<String, Value>>,
+    /// Maps old field names to new ones. An existing index on an old field is considered to be on
+    /// the new one, which avoids rebuilding indexes during a rename. The index keeps the old field.
+    pub field_aliases: Option<BTreeMap<String, String>>,
+    /// Overrides autoRecreateOnConflict of the configuration for this collection.
+    pub force_recreate_on_conflict: Option<bool>,
+    /// Hides the unmanaged indexes when preserveUnmanagedIndexes is set, so they don't affect query
+    /// planning. Setting it to false unhides them again.
+    pub hide_all_unmanaged: Option<bool>,
+    /// How long the progress of an index build is reported in the status. The default is 300.
+    pub index_build_timeout_seconds: Option<u64>,
+    /// Overrides indexNameTemplate of the configuration for this collection.
+    pub index_name_template: Option<String>,
+    /// When absent, the indexes of the collection are not managed. An empty list drops all
+    /// indexes, but only when confirmEmptyIndexes is set to true.
     pub indexes: Option<Vec<Index>>,
+    /// The collection is only created when there are indexes or search indexes for it. Otherwise
+    /// the first write of an application creates it.
+    pub lazy_creation: Option<bool>,
+    /// How often the pipeline of a materialized view is run. The default is 3600.
+    pub materialize_interval_seconds: Option<u64>,
+    /// Instead of creating a view, the pipeline is run periodically against viewOn and its result
+    /// replaces the contents of the collection with $out.
+    pub materialized: Option<bool>,
     pub max: Option<u64>,
+    /// The time limit for listing, creating and dropping indexes.
+    pub max_time_ms: Option<u64>,
     pub name: Option<String>,
+    /// The natural order in which a capped collection is scanned. It is informational only and
+    /// reported in the status as cappedScanDirection.
+    pub natural_scan_direction: Option<Direction>,
+    pub pipeline: Option<Vec<Map<String, Value>>>,
+    /// A unique index with a partial filter is only created when the documents that match the
+    /// filter have no duplicate keys.
+    pub pre_check_partial_unique: Option<bool>,
+    pub preserve_unmanaged_indexes: Option<bool>,
+    pub reconcile_interval_seconds: Option<u64>,
+    /// Either continuous, which is the default, or onSpecChange. The latter only reconciles when
+    /// the spec changes or a reconcile is requested with an annotation.
+    pub reconcile_mode: Option<ReconcileMode>,
+    /// When absent, the Atlas Search indexes of the collection are not managed.
+    pub search_indexes: Option<Vec<SearchIndex>>,
     pub size: Option<u64>,
     pub time_series: Option<TimeSeries>,
     pub validator: Option<Map<String, Value>>,
     pub validation_action: Option<ValidationAction>,
     pub validation_level: Option<ValidationLevel>,
+    pub view_on: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MongoCollectionStatus {
+    #[serde(flatten)]
+    pub base: Status,
+    /// Either "ascending" or "descending" for a capped collection.
+    pub capped_scan_direction: Option<String>,
+    /// The UUID of the collection when it was last reconciled.
+    pub collection_uuid: Option<String>,
+    /// A fingerprint of the indexes when the collection last needed no changes. Other indexes
+    /// while the generation is the same mean a state regression.
+    pub converged_fingerprint: Option<String>,
+    /// How far the creation of the collection got, which is "CreatedCollection" or
+    /// "CreatedIndexes". After a capped migration it is "MigratedToCapped" until the indexes are
+    /// created again.
+    pub creation_stage: Option<String>,
+    /// The value of the annotation mongo-collections.pincette.net/force-sync that was last handled.
+    pub force_sync: Option<String>,
+    /// The percentage of the running index build that is done. It is cleared when a reconciliation
+    /// changes nothing.
+    pub index_build_progress: Option<f64>,
+    /// The index builds the reconciliation waits for. The state is "Building" when currentOp shows
+    /// the build and "Unknown" when a step-down of the primary interrupted the wait for it.
+    pub index_builds: Option<Vec<IndexBuild>>,
+    /// The use of the indexes, when collectIndexStats is set in the configuration.
+    pub index_stats: Option<Vec<IndexStats>>,
+    /// When the pipeline of a materialized view last ran.
+    pub materialized_at: Option<String>,
+    /// The generation of the resource that was last reconciled successfully.
+    pub observed_generation: Option<i64>,
+    /// The reconcile mode of the spec.
+    pub reconcile_mode: Option<ReconcileMode>,
+    /// When the collection was last reconciled in the mode onSpecChange.
+    pub reconciled_at: Option<String>,
+    /// The unmanaged indexes that were unhidden instead of dropped, because
+    /// preserveUnmanagedIndexes was turned off. They are kept until dropHiddenUnmanagedIndexes is
+    /// set.
+    pub released_indexes: Option<Vec<String>>,
+    /// The value of the annotation reconcile.pincette.net/requestedAt that was last handled.
+    pub requested_at: Option<String>,
+    /// How the buckets of a time series collection are doing.
+    pub timeseries: Option<TimeSeriesSummary>,
+    /// The problems in the indexes of the spec that keep it from being reconciled.
+    pub validation_errors: Option<Vec<ValidationError>>,
+}
+
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    kind = "MongoCollectionSet",
+    group = "pincette.net",
+    version = "v1",
+    namespaced,
+    category = "controllers",
+    shortname = "mcs",
+    printcolumn = r#"{"name":"Health", "type":"string", "jsonPath":".status.health.status"}"#,
+    printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[kube(status = "Status")]
+#[serde(rename_all = "camelCase")]
+pub struct MongoCollectionSetSpec {
+    /// Each entry must have a name.
+    pub collections: Vec<MongoCollectionSpec>,
+}
+
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    kind = "MongoDatabase",
+    group = "pincette.net",
+    version = "v1",
+    category = "controllers",
+    shortname = "mdb",
+    printcolumn = r#"{"name":"Health", "type":"string", "jsonPath":".status.health.status"}"#,
+    printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[kube(status = "Status")]
+#[serde(rename_all = "camelCase")]
+pub struct MongoDatabaseSpec {
+    /// The default collation for the collections that refer to this database.
+    pub collation: Option<Collation>,
+    pub name: String,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
@@ -129,13 +273,56 @@ pub enum CollationStrength {
     Identical = 5,
 }
 
-#[derive(Clone, Debug, Deserialize_repr, Serialize_repr, JsonSchema_repr, PartialEq)]
+#[derive(Clone, Debug, Serialize_repr, PartialEq)]
 #[repr(i32)]
 pub enum Direction {
     Ascending = 1,
     Descending = -1,
 }
 
+// Besides 1 and -1 the names "asc", "ascending", "desc" and "descending" are accepted.
+impl<'de> Deserialize<'de> for Direction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match DirectionValue::deserialize(deserializer)? {
+            DirectionValue::Number(1) => Ok(Direction::Ascending),
+            DirectionValue::Number(-1) => Ok(Direction::Descending),
+            DirectionValue::Number(v) => Err(D::Error::custom(format!(
+                "invalid direction {v}, expected 1 or -1"
+            ))),
+            DirectionValue::Name(v) => match v.to_lowercase().as_str() {
+                "asc" | "ascending" => Ok(Direction::Ascending),
+                "desc" | "descending" => Ok(Direction::Descending),
+                _ => Err(D::Error::custom(format!(
+                    "invalid direction {v}, expected asc, ascending, desc or descending"
+                ))),
+            },
+        }
+    }
+}
+
+impl JsonSchema for Direction {
+    fn schema_name() -> Cow<'static, str> {
+        "Direction".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Either 1, -1, \"asc\", \"ascending\", \"desc\" or \"descending\".",
+            "x-kubernetes-int-or-string": true
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DirectionValue {
+    Number(i32),
+    Name(String),
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum Granularity {
@@ -150,13 +337,62 @@ pub struct Index {
     pub options: Option<Options>,
 }
 
+impl Index {
+    /// MongoDB stores a weight of 1 for every text key that has no weight.
+    pub fn normalized_options(&self) -> Option<Options> {
+        let text_keys: Vec<&Key> = self
+            .keys
+            .iter()
+            .filter(|k| matches!(k.index_type, Some(IndexType::Text)))
+            .collect();
+
+        if text_keys.is_empty() {
+            return self.options.clone();
+        }
+
+        let mut options = self.options.clone().unwrap_or_default();
+        let weights = options.weights.get_or_insert_with(BTreeMap::new);
+
+        for k in text_keys {
+            weights.entry(k.field.clone()).or_insert(1);
+        }
+
+        Some(options)
+    }
+}
+
 impl PartialEq for Index {
     fn eq(&self, other: &Self) -> bool {
+        let (options, other_options) = (self.normalized_options(), other.normalized_options());
+
         same_keys(self.keys.as_slice(), other.keys.as_slice())
-            && (self.options == other.options || is_default_option(&self.options, &other.options))
+            && (options == other_options || is_default_option(&options, &other_options))
     }
 }
 
+/// An index build that was interrupted or that is still running.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexBuild {
+    pub name: String,
+    /// The operation of the build in currentOp, once the operator has found it there.
+    pub op_id: Option<String>,
+    /// When the build was submitted or, for a build found in currentOp, when it started.
+    pub started_at: Option<String>,
+    /// Either "Building" or "Unknown".
+    pub state: String,
+}
+
+/// The use of an index as $indexStats reports it.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    /// When the server started counting, which is when it started or the index was created.
+    pub last_reset: Option<String>,
+    pub name: String,
+    pub ops_count: u64,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub enum IndexType {
@@ -168,7 +404,7 @@ pub enum IndexType {
     TwoDimensionalSphere,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[derive(Clone, Debug, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Key {
     pub direction: Option<Direction>,
@@ -176,21 +412,110 @@ pub struct Key {
     pub index_type: Option<IndexType>,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+// Besides an object with the field "field", a key can be a single entry with the field name and
+// the direction or index type, as in mongosh. Both forms can be mixed in one index. A single entry
+// is always the short form, so {field: text} is a text index on the field "field".
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let map = Map::<String, Value>::deserialize(deserializer)?;
+
+        if map.len() > 1 || map.contains_key("direction") || map.contains_key("indexType") {
+            let key: KeyValue =
+                serde_json::from_value(Value::Object(map)).map_err(D::Error::custom)?;
+
+            return Ok(Key {
+                direction: key.direction,
+                field: key.field,
+                index_type: key.index_type,
+            });
+        }
+
+        let mut entries = map.into_iter();
+
+        match (entries.next(), entries.next()) {
+            (Some((field, value)), None) => {
+                match serde_json::from_value::<IndexType>(value.clone()) {
+                    Ok(t) => Ok(Key {
+                        direction: None,
+                        field,
+                        index_type: Some(t),
+                    }),
+                    Err(_) => Ok(Key {
+                        direction: Some(serde_json::from_value(value).map_err(D::Error::custom)?),
+                        field,
+                        index_type: None,
+                    }),
+                }
+            }
+            _ => Err(D::Error::custom(
+                "invalid key, expected the field \"field\" or a single entry such as {a: 1}",
+            )),
+        }
+    }
+}
+
+impl JsonSchema for Key {
+    fn schema_name() -> Cow<'static, str> {
+        "Key".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Either an object with field and direction or indexType, or a single \
+                entry with the field name and the direction or index type, such as {a: 1} or \
+                {b: text}.",
+            "type": "object",
+            "properties": {
+                "direction": generator.subschema_for::<Direction>(),
+                "field": {"type": "string"},
+                "indexType": generator.subschema_for::<IndexType>()
+            },
+            "x-kubernetes-preserve-unknown-fields": true
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyValue {
+    direction: Option<Direction>,
+    field: String,
+    index_type: Option<IndexType>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Options {
     pub bits: Option<u32>,
     pub collation: Option<Collation>,
+    /// Documents the index. It is sent along with the creation of the index, but MongoDB doesn't
+    /// store it, so it is never compared.
+    pub comment: Option<String>,
     pub default_language: Option<String>,
     pub expire_after_seconds: Option<u64>,
+    /// Index options the operator doesn't know yet. They are added to the other options when the
+    /// index is created. MongoDB may not return them when listing indexes, so they are never
+    /// compared.
+    pub extra_options: Option<Map<String, Value>>,
     pub hidden: Option<bool>,
     pub language_override: Option<String>,
     pub max: Option<f64>,
     pub min: Option<f64>,
     pub name: Option<String>,
     pub partial_filter_expression: Option<BTreeMap<String, Value>>,
+    /// Together with unique, the index is first created or changed to reject new duplicate keys.
+    /// It only becomes unique when the annotation reconcile.pincette.net/convert-to-unique is
+    /// "true". This is never compared.
+    pub prepare_unique: Option<bool>,
     pub sparse: Option<bool>,
+    /// When absent, an existing 2dsphere index of any version matches. A version that differs from
+    /// the existing one causes a rebuild.
     pub sphere_index_version: Option<u32>,
+    /// When absent, an existing text index of any version matches. A version that differs from the
+    /// existing one causes a rebuild.
     pub text_index_version: Option<u32>,
     pub unique: Option<bool>,
     pub weights: Option<BTreeMap<String, u32>>,
@@ -198,6 +523,75 @@ pub struct Options {
 }
 
 impl Options {
+    /// The names of the options that differ, with the semantics of the equality.
+    pub fn diff(&self, other: &Self) -> Vec<&'static str> {
+        [
+            ("bits", self.bits == other.bits),
+            ("collation", self.collation == other.collation),
+            (
+                "defaultLanguage",
+                self.default_language == other.default_language
+                    || is_default_language(&self.default_language, &other.default_language),
+            ),
+            (
+                "expireAfterSeconds",
+                self.expire_after_seconds == other.expire_after_seconds,
+            ),
+            ("hidden", self.hidden == other.hidden),
+            (
+                "languageOverride",
+                self.language_override == other.language_override
+                    || is_default_language_override(
+                        &self.language_override,
+                        &other.language_override,
+                    ),
+            ),
+            ("max", self.max == other.max),
+            ("min", self.min == other.min),
+            (
+                "partialFilterExpression",
+                self.partial_filter_expression
+                    .as_ref()
+                    .map(normalized_numbers)
+                    == other
+                        .partial_filter_expression
+                        .as_ref()
+                        .map(normalized_numbers),
+            ),
+            (
+                "sparse",
+                self.sparse.unwrap_or(false) == other.sparse.unwrap_or(false),
+            ),
+            (
+                "sphereIndexVersion",
+                self.sphere_index_version == other.sphere_index_version
+                    || self.sphere_index_version.is_none()
+                    || other.sphere_index_version.is_none(),
+            ),
+            (
+                "textIndexVersion",
+                self.text_index_version == other.text_index_version
+                    || self.text_index_version.is_none()
+                    || other.text_index_version.is_none(),
+            ),
+            ("unique", self.unique == other.unique),
+            (
+                "weights",
+                self.weights == other.weights || self.weights.is_none() || other.weights.is_none(),
+            ),
+            (
+                "wildcardProjection",
+                self.wildcard_projection == other.wildcard_projection,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, same)| !same)
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    // The 2dsphere index version isn't checked, because any version is accepted when it isn't
+    // specified.
     fn is_default(&self) -> bool {
         self.bits.is_none_or(|v| v == 26)
             && self.collation.is_none()
@@ -209,7 +603,6 @@ impl Options {
             && self.min.is_none_or(|v| v == -180.0)
             && self.partial_filter_expression.is_none()
             && self.sparse.is_none_or(|v| !v)
-            && self.sphere_index_version.is_none()
             && self.text_index_version.is_none()
             && self.unique.is_none_or(|v| !v)
             && self.weights.is_none()
@@ -217,32 +610,118 @@ impl Options {
     }
 }
 
-// The name is excluded because it may be a generated name.
+// The name is excluded because it may be a generated name. The comment and the extra options are
+// only informational. An unspecified 2dsphere index version accepts any version, while a specified
+// one that differs causes a rebuild.
 impl PartialEq for Options {
     fn eq(&self, other: &Self) -> bool {
-        self.bits == other.bits
-            && self.collation == other.collation
-            && (self.default_language == other.default_language
-                || is_default_language(&self.default_language, &other.default_language))
-            && self.expire_after_seconds == other.expire_after_seconds
-            && self.hidden == other.hidden
-            && (self.language_override == other.language_override
-                || is_default_language_override(&self.language_override, &other.language_override))
-            && self.max == other.max
-            && self.min == other.min
-            && self.partial_filter_expression == other.partial_filter_expression
-            && (self.sphere_index_version == other.sphere_index_version
-                || self.sphere_index_version.is_none()
-                || other.sphere_index_version.is_none())
-            && (self.text_index_version == other.text_index_version
-                || self.text_index_version.is_none()
-                || other.text_index_version.is_none())
-            && self.unique == other.unique
-            && (self.weights == other.weights || self.weights.is_none() || other.weights.is_none())
-            && self.wildcard_projection == other.wildcard_projection
+        self.diff(other).is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReconcileMode {
+    #[default]
+    Continuous,
+    OnSpecChange,
+}
+
+/// An Atlas Search index. Its definition is updated in place, but its type can't be changed.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndex {
+    pub definition: Map<String, Value>,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub search_index_type: Option<SearchIndexType>,
+    /// Overrides the field storedSource of the definition.
+    pub stored_source: Option<StoredSource>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchIndexType {
+    Search,
+    VectorSearch,
+}
+
+/// Which fields of the documents are stored in an Atlas Search index.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StoredSource {
+    All,
+    Exclude(Vec<String>),
+    Include(Vec<String>),
+    None,
+}
+
+impl StoredSource {
+    fn to_value(&self) -> Value {
+        match self {
+            StoredSource::All => Value::Bool(true),
+            StoredSource::Exclude(f) => json!({"exclude": f}),
+            StoredSource::Include(f) => json!({"include": f}),
+            StoredSource::None => Value::Bool(false),
+        }
     }
 }
 
+// The field lists can't be empty.
+impl<'de> Deserialize<'de> for StoredSource {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match StoredSourceValue::deserialize(deserializer)? {
+            StoredSourceValue::Flag(true) => Ok(StoredSource::All),
+            StoredSourceValue::Flag(false) => Ok(StoredSource::None),
+            StoredSourceValue::Fields {
+                exclude: Some(f),
+                include: None,
+            } if !f.is_empty() => Ok(StoredSource::Exclude(f)),
+            StoredSourceValue::Fields {
+                exclude: None,
+                include: Some(f),
+            } if !f.is_empty() => Ok(StoredSource::Include(f)),
+            StoredSourceValue::Fields { .. } => Err(D::Error::custom(
+                "invalid storedSource, expected either a non-empty include or a non-empty exclude",
+            )),
+        }
+    }
+}
+
+impl JsonSchema for StoredSource {
+    fn schema_name() -> Cow<'static, str> {
+        "StoredSource".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Either true, false, {\"include\": [...]} or {\"exclude\": [...]}.",
+            "x-kubernetes-preserve-unknown-fields": true
+        })
+    }
+}
+
+impl Serialize for StoredSource {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.to_value().serialize(serializer)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredSourceValue {
+    Flag(bool),
+    Fields {
+        exclude: Option<Vec<String>>,
+        include: Option<Vec<String>>,
+    },
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TimeSeries {
@@ -253,6 +732,19 @@ pub struct TimeSeries {
     pub time_field: String,
 }
 
+/// How a time series collection buckets its measurements.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSeriesSummary {
+    /// The average time between the first and the last measurement of the 1000 most recent
+    /// buckets, rounded to seconds.
+    pub average_bucket_span_seconds: Option<f64>,
+    pub bucket_count: Option<u64>,
+    /// Whether the collection system.buckets.<name> exists. When it doesn't, the collection
+    /// probably isn't a time series collection.
+    pub buckets_collection: bool,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum ValidationAction {
@@ -260,6 +752,19 @@ pub enum ValidationAction {
     Warn,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationError {
+    /// The key or option that is invalid.
+    pub field: String,
+    /// The position of the index in the field indexes, starting from 0.
+    pub index: usize,
+    pub message: String,
+    /// The specified or generated name of the index.
+    pub name: String,
+    pub remediation: String,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum ValidationLevel {
@@ -292,10 +797,195 @@ fn is_default_language_override(v1: &Option<String>, v2: &Option<String>) -> boo
     is_default_comparison(v1.as_ref(), v2.as_ref(), |v| v == "language")
 }
 
+// Options with only default values are the same, unless both have a 2dsphere index version and they
+// differ.
 fn is_default_option(v1: &Option<Options>, v2: &Option<Options>) -> bool {
+    let version = |v: &Option<Options>| v.as_ref().and_then(|o| o.sphere_index_version);
+
     is_default_comparison(v1.as_ref(), v2.as_ref(), |v| v.is_default())
+        && !matches!((version(v1), version(v2)), (Some(a), Some(b)) if a != b)
+}
+
+fn non_text_keys(keys: &[Key]) -> impl Iterator<Item = &Key> {
+    keys.iter()
+        .filter(|k| !matches!(k.index_type, Some(IndexType::Text)))
+}
+
+// A number without a fraction is the same as the integer, because MongoDB may have stored either.
+fn normalized_number(value: &Value) -> Value {
+    match value {
+        Value::Array(a) => Value::Array(a.iter().map(normalized_number).collect()),
+        Value::Number(n) => n
+            .as_f64()
+            .filter(|f| n.is_f64() && f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+            .map_or_else(|| value.clone(), |f| json!(f as i64)),
+        Value::Object(o) => Value::Object(
+            o.iter()
+                .map(|(k, v)| (k.clone(), normalized_number(v)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+fn normalized_numbers(map: &BTreeMap<String, Value>) -> BTreeMap<String, Value> {
+    map.iter()
+        .map(|(k, v)| (k.clone(), normalized_number(v)))
+        .collect()
 }
 
+// The order of the keys matters, except for text keys, which are reconstructed from the weights.
 fn same_keys(v1: &[Key], v2: &[Key]) -> bool {
-    v1.len() == v2.len() && v1.iter().all(|k| v2.contains(k))
+    v1.len() == v2.len()
+        && v1.iter().all(|k| v2.contains(k))
+        && non_text_keys(v1).eq(non_text_keys(v2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: Value) -> Key {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn direction_names() {
+        let direction = |value: Value| serde_json::from_value::<Direction>(value).unwrap();
+
+        assert_eq!(direction(json!("asc")), Direction::Ascending);
+        assert_eq!(direction(json!("Ascending")), Direction::Ascending);
+        assert_eq!(direction(json!("desc")), Direction::Descending);
+        assert_eq!(direction(json!("DESCENDING")), Direction::Descending);
+    }
+
+    #[test]
+    fn direction_numbers() {
+        let direction = |value: Value| serde_json::from_value::<Direction>(value).unwrap();
+
+        assert_eq!(direction(json!(1)), Direction::Ascending);
+        assert_eq!(direction(json!(-1)), Direction::Descending);
+        assert_eq!(
+            serde_json::to_value(Direction::Descending).unwrap(),
+            json!(-1)
+        );
+    }
+
+    #[test]
+    fn invalid_direction() {
+        assert!(serde_json::from_value::<Direction>(json!(2)).is_err());
+        assert!(serde_json::from_value::<Direction>(json!("up")).is_err());
+    }
+
+    #[test]
+    fn invalid_key() {
+        assert!(serde_json::from_value::<Key>(json!({"a": 1, "b": 1})).is_err());
+        assert!(serde_json::from_value::<Key>(json!({"a": "unknown"})).is_err());
+    }
+
+    #[test]
+    fn long_form_key() {
+        assert_eq!(
+            key(json!({"field": "a", "direction": -1})),
+            Key {
+                direction: Some(Direction::Descending),
+                field: "a".to_string(),
+                index_type: None,
+            }
+        );
+        assert_eq!(
+            key(json!({"field": "b", "indexType": "text"})),
+            Key {
+                direction: None,
+                field: "b".to_string(),
+                index_type: Some(IndexType::Text),
+            }
+        );
+    }
+
+    #[test]
+    fn partially_weighted_text_index() {
+        let index = |weights: Value| {
+            serde_json::from_value::<Index>(json!({
+                "keys": [
+                    {"field": "title", "indexType": "text"},
+                    {"field": "body", "indexType": "text"}
+                ],
+                "options": {"weights": weights}
+            }))
+            .unwrap()
+        };
+        let specified = index(json!({"title": 10}));
+
+        assert_eq!(specified, index(json!({"body": 1, "title": 10})));
+        assert_ne!(specified, index(json!({"body": 2, "title": 10})));
+        assert_ne!(specified, index(json!({"body": 1, "title": 5})));
+    }
+
+    #[test]
+    fn short_form_key() {
+        assert_eq!(
+            key(json!({"a": 1})),
+            Key {
+                direction: Some(Direction::Ascending),
+                field: "a".to_string(),
+                index_type: None,
+            }
+        );
+        assert_eq!(
+            key(json!({"field": "text"})),
+            Key {
+                direction: None,
+                field: "field".to_string(),
+                index_type: Some(IndexType::Text),
+            }
+        );
+    }
+
+    #[test]
+    fn sphere_index_version() {
+        let index = |options: Value| {
+            serde_json::from_value::<Index>(json!({
+                "keys": [{"field": "location", "indexType": "2dsphere"}],
+                "options": options
+            }))
+            .unwrap()
+        };
+
+        assert_ne!(
+            index(json!({"sphereIndexVersion": 2})),
+            index(json!({"sphereIndexVersion": 3}))
+        );
+        assert_eq!(index(json!({})), index(json!({"sphereIndexVersion": 3})));
+        assert_eq!(index(json!(null)), index(json!({"sphereIndexVersion": 3})));
+        assert_eq!(
+            index(json!({"sphereIndexVersion": 3})),
+            index(json!({"sphereIndexVersion": 3}))
+        );
+    }
+
+    #[test]
+    fn text_index_version() {
+        let index = |options: Value| {
+            serde_json::from_value::<Index>(json!({
+                "keys": [{"field": "title", "indexType": "text"}],
+                "options": options
+            }))
+            .unwrap()
+        };
+
+        assert_ne!(
+            index(json!({"textIndexVersion": 2})),
+            index(json!({"textIndexVersion": 3}))
+        );
+        assert_eq!(index(json!(null)), index(json!({"textIndexVersion": 3})));
+        assert_eq!(
+            index(json!({"weights": {"title": 5}})),
+            index(json!({"textIndexVersion": 3, "weights": {"title": 5}}))
+        );
+        assert_ne!(
+            index(json!({"textIndexVersion": 2, "weights": {"title": 5}})),
+            index(json!({"textIndexVersion": 3, "weights": {"title": 5}}))
+        );
+    }
 }
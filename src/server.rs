@@ -0,0 +1,93 @@
+use log::{error, info};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const MAX_REQUEST_SIZE: usize = 8192;
+
+pub struct Response {
+    pub body: String,
+    pub content_type: &'static str,
+}
+
+/// A minimal HTTP listener for GET requests. The handler receives the path and the query
+/// parameters. When it returns nothing, the response is a 404.
+pub async fn serve<H>(port: u16, handler: H) -> std::io::Result<()>
+where
+    H: Fn(&str, &BTreeMap<String, String>) -> Option<Response> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    let handler = Arc::new(handler);
+
+    info!("Listening on port {port}");
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let h = handler.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, h.as_ref()).await {
+                error!("HTTP request failed: {e}");
+            }
+        });
+    }
+}
+
+async fn handle<H>(mut stream: TcpStream, handler: &H) -> std::io::Result<()>
+where
+    H: Fn(&str, &BTreeMap<String, String>) -> Option<Response>,
+{
+    let request = read_head(&mut stream).await?;
+    let response = request_target(&request).and_then(|t| {
+        let (path, query) = t.split_once('?').unwrap_or((t.as_str(), ""));
+
+        handler(path, &parse_query(query))
+    });
+    let text = match response {
+        Some(r) => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            r.content_type,
+            r.body.len(),
+            r.body
+        ),
+        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    stream.write_all(text.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn parse_query(query: &str) -> BTreeMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<String> {
+    let mut buffer = vec![0; MAX_REQUEST_SIZE];
+    let mut size = 0;
+
+    while size < buffer.len() && !buffer[..size].windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buffer[size..]).await?;
+
+        if n == 0 {
+            break;
+        }
+
+        size += n;
+    }
+
+    Ok(String::from_utf8_lossy(&buffer[..size]).to_string())
+}
+
+fn request_target(request: &str) -> Option<String> {
+    let mut parts = request.lines().next()?.split_whitespace();
+
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(t)) => Some(t.to_string()),
+        _ => None,
+    }
+}
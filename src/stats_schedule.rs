@@ -0,0 +1,38 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Tells when statistics of an object are due to be refreshed, which is at most once per interval
+/// per kind of statistics.
+pub struct StatsSchedule {
+    interval: Duration,
+    refreshed: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl StatsSchedule {
+    pub fn new(interval: Duration) -> Self {
+        StatsSchedule {
+            interval,
+            refreshed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns whether the statistics are due, in which case they are considered refreshed now.
+    pub fn due(&self, kind: &str, object: &str) -> bool {
+        let Ok(mut refreshed) = self.refreshed.lock() else {
+            return true;
+        };
+        let now = Instant::now();
+
+        refreshed.retain(|_, t| now.duration_since(*t) < self.interval);
+
+        match refreshed.entry((kind.to_string(), object.to_string())) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(e) => {
+                e.insert(now);
+                true
+            }
+        }
+    }
+}